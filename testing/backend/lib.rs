@@ -1,30 +1,301 @@
 use std::collections::HashMap;
+use hmac::Hmac;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+/// Default PBKDF2 iteration count for newly hashed credentials.
+///
+/// Raising this value over time lets [`User::rehash_if_needed`] transparently
+/// upgrade stored hashes on the next successful login.
+pub const DEFAULT_PASSWORD_ITERATIONS: i32 = 600_000;
+
+/// Argon2id cost parameters (OWASP baseline: 19 MiB of memory, 2 passes, single
+/// lane). These are distinct from [`DEFAULT_PASSWORD_ITERATIONS`], which counts
+/// PBKDF2 HMAC rounds — feeding that round count in as an Argon2 time cost would
+/// turn a credential op into a multi-minute hang.
+const ARGON2_M_COST_KIB: u32 = 19 * 1024;
+const ARGON2_T_COST: u32 = 2;
+const ARGON2_P_COST: u32 = 1;
+
+/// Key-derivation function used to stretch a user's password, mirroring
+/// Vaultwarden's `client_kdf_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ClientKdfType {
+    #[default]
+    Pbkdf2,
+    Argon2id,
+}
+
+/// Lifecycle state of an account, following Vaultwarden's `UserStatus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum AccountStatus {
+    /// Created but not yet usable; awaiting acceptance of an invitation.
+    #[default]
+    Invited,
+    /// Fully usable account.
+    Active,
+    /// Explicitly disabled; the account exists but cannot be used.
+    Disabled,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: u64,
     pub name: String,
     pub email: String,
-    pub active: bool,
+    #[serde(default)]
+    pub status: AccountStatus,
+    #[serde(default)]
+    pub password_hash: Vec<u8>,
+    #[serde(default)]
+    pub salt: Vec<u8>,
+    #[serde(default)]
+    pub password_iterations: i32,
+    #[serde(default)]
+    pub client_kdf_type: ClientKdfType,
+    #[serde(default)]
+    pub role: UserRole,
+    /// Random value rotated on every credential change; tokens issued before
+    /// the current stamp are treated as invalid.
+    #[serde(default)]
+    pub security_stamp: String,
+    /// Exception allowing a single token to remain valid across rotations until
+    /// it is cleared (e.g. the token driving a password-reset flow).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stamp_exception: Option<String>,
+}
+
+impl User {
+    /// Derive a credential from `plaintext` using a freshly generated salt and
+    /// the default iteration count, replacing any previously stored hash. The
+    /// security stamp is rotated so tokens issued against the old credential
+    /// stop validating.
+    pub fn set_password(&mut self, plaintext: &str) {
+        self.derive_credential(plaintext, DEFAULT_PASSWORD_ITERATIONS);
+        self.rotate_security_stamp();
+    }
+
+    /// Re-derive hash/salt/iterations from `plaintext` at `iterations`, replacing
+    /// the stored credential *without* touching the security stamp. Callers that
+    /// represent a real credential change rotate the stamp themselves.
+    fn derive_credential(&mut self, plaintext: &str, iterations: i32) {
+        let mut salt = vec![0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        self.salt = salt;
+        self.password_iterations = iterations;
+        self.password_hash = derive_hash(
+            plaintext,
+            &self.salt,
+            self.password_iterations,
+            self.client_kdf_type,
+        );
+    }
+
+    /// Update the account's email, rotating the security stamp.
+    pub fn set_email(&mut self, email: String) {
+        self.email = email;
+        self.rotate_security_stamp();
+    }
+
+    /// Regenerate the security stamp from fresh CSPRNG bytes.
+    ///
+    /// Any outstanding [`stamp_exception`](Self::stamp_exception) is deliberately
+    /// preserved: its whole purpose is to keep one token valid across the very
+    /// rotation that a credential change triggers. Clear it explicitly with
+    /// [`clear_stamp_exception`](Self::clear_stamp_exception) once consumed.
+    pub fn rotate_security_stamp(&mut self) {
+        let mut bytes = [0u8; 20];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        self.security_stamp = bs58::encode(&bytes).into_string();
+    }
+
+    /// Allow a single token to survive stamp rotations until
+    /// [`clear_stamp_exception`](Self::clear_stamp_exception) is called, used to
+    /// keep a reset/confirmation flow working across the stamp change it triggers.
+    pub fn set_stamp_exception(&mut self, token: &str) {
+        self.stamp_exception = Some(token.to_string());
+    }
+
+    /// Drop any outstanding stamp exception, once the flow it covered is done.
+    pub fn clear_stamp_exception(&mut self) {
+        self.stamp_exception = None;
+    }
+
+    /// Like [`set_password`](Self::set_password) but pins an explicit iteration
+    /// count and leaves the security stamp unchanged, used to reconstruct legacy
+    /// records below the current default.
+    pub fn set_password_with_iterations(&mut self, plaintext: &str, iterations: i32) {
+        self.derive_credential(plaintext, iterations);
+    }
+
+    /// Recompute the hash from the stored salt/iterations and compare it to the
+    /// stored value in constant time.
+    pub fn verify_password(&self, plaintext: &str) -> bool {
+        if self.password_hash.is_empty() {
+            return false;
+        }
+        let candidate = derive_hash(
+            plaintext,
+            &self.salt,
+            self.password_iterations,
+            self.client_kdf_type,
+        );
+        candidate.ct_eq(&self.password_hash).into()
+    }
+
+    /// If the stored iteration count is below the current default, re-derive the
+    /// hash with `plaintext` so a successful login silently upgrades the record.
+    ///
+    /// The password is unchanged, so the security stamp is deliberately *not*
+    /// rotated — a transparent rehash must not invalidate outstanding tokens.
+    ///
+    /// Returns `true` when the hash was upgraded.
+    pub fn rehash_if_needed(&mut self, plaintext: &str) -> bool {
+        if self.password_iterations >= DEFAULT_PASSWORD_ITERATIONS {
+            return false;
+        }
+        self.derive_credential(plaintext, DEFAULT_PASSWORD_ITERATIONS);
+        true
+    }
 }
 
-#[derive(Debug)]
+/// Stretch `plaintext` with the chosen KDF, producing the raw hash bytes.
+fn derive_hash(plaintext: &str, salt: &[u8], iterations: i32, kdf: ClientKdfType) -> Vec<u8> {
+    match kdf {
+        ClientKdfType::Pbkdf2 => {
+            let mut out = vec![0u8; 32];
+            let _ = pbkdf2::pbkdf2::<Hmac<Sha256>>(
+                plaintext.as_bytes(),
+                salt,
+                iterations.max(1) as u32,
+                &mut out,
+            );
+            out
+        }
+        ClientKdfType::Argon2id => {
+            // Argon2 uses its own cost model; the PBKDF2 round count does not map
+            // onto the time cost, so derive the parameters independently.
+            let params = argon2::Params::new(
+                ARGON2_M_COST_KIB,
+                ARGON2_T_COST,
+                ARGON2_P_COST,
+                Some(32),
+            )
+            .expect("valid argon2 params");
+            let argon2 = argon2::Argon2::new(
+                argon2::Algorithm::Argon2id,
+                argon2::Version::V0x13,
+                params,
+            );
+            let mut out = vec![0u8; 32];
+            argon2
+                .hash_password_into(plaintext.as_bytes(), salt, &mut out)
+                .expect("argon2 hashing");
+            out
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum UserRole {
     Admin,
     Editor,
+    #[default]
     Viewer,
 }
 
+/// An operation that may be subject to role-based authorization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    DeleteUser,
+    EditUser,
+    ViewUser,
+}
+
+/// Check whether `actor` is permitted to perform `action`.
+///
+/// Follows the organization-role model used by password-manager backends:
+/// `Admin` may do anything, `Editor` may edit and view, `Viewer` may only view.
+pub fn authorize(actor: &User, action: Action) -> Result<(), String> {
+    let allowed = match action {
+        Action::DeleteUser => actor.role == UserRole::Admin,
+        Action::EditUser => matches!(actor.role, UserRole::Admin | UserRole::Editor),
+        Action::ViewUser => true,
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!("{:?} is not permitted to perform {:?}", actor.role, action))
+    }
+}
+
+/// A single-use, expiring token proving ownership of an email address,
+/// mirroring the `email_token_credentials` table in OpenFairDB.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailTokenCredential {
+    pub user_id: u64,
+    pub username: String,
+    pub email: String,
+    pub nonce: String,
+    pub expires_at: std::time::SystemTime,
+}
+
+/// A cryptographically random, expiring bearer token owned by a user.
+///
+/// Replaces the old timestamp-only string and underpins the email-verification
+/// and password-reset flows. The `security_stamp` captured at issue time lets
+/// the repository reject tokens minted before a credential change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Token {
+    /// URL-safe base58 encoding of 32 CSPRNG bytes.
+    pub value: String,
+    pub user_id: u64,
+    pub issued_at: std::time::SystemTime,
+    pub expires_at: std::time::SystemTime,
+    pub security_stamp: String,
+}
+
+/// Default token lifetime (1 hour).
+pub const DEFAULT_TOKEN_TTL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+impl Token {
+    /// Mint a fresh token for `user` that expires `ttl` from now.
+    pub fn issue(user: &User, ttl: std::time::Duration) -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let issued_at = std::time::SystemTime::now();
+        Token {
+            value: bs58::encode(&bytes).into_string(),
+            user_id: user.id,
+            issued_at,
+            expires_at: issued_at + ttl,
+            security_stamp: user.security_stamp.clone(),
+        }
+    }
+
+    /// Return `true` if the token has not yet expired as of `now`.
+    pub fn verify(&self, now: std::time::SystemTime) -> bool {
+        now < self.expires_at
+    }
+}
+
 pub trait Repository<T> {
     fn find_by_id(&self, id: u64) -> Option<T>;
     fn save(&mut self, item: T) -> Result<(), String>;
     fn delete(&mut self, id: u64) -> bool;
+    /// Return every stored item. Needed by management surfaces that list
+    /// accounts rather than looking them up by id.
+    fn all(&self) -> Vec<T>;
 }
 
 pub struct InMemoryUserRepository {
     users: HashMap<u64, User>,
     next_id: u64,
+    email_tokens: HashMap<String, EmailTokenCredential>,
+    tokens: HashMap<String, Token>,
 }
 
 impl InMemoryUserRepository {
@@ -32,16 +303,194 @@ impl InMemoryUserRepository {
         Self {
             users: HashMap::new(),
             next_id: 1,
+            email_tokens: HashMap::new(),
+            tokens: HashMap::new(),
+        }
+    }
+
+    /// Mint and store a token for the user with `user_id`, returning it.
+    pub fn issue_token(&mut self, user_id: u64) -> Result<Token, String> {
+        let user = self.users.get(&user_id).ok_or_else(|| "User not found".to_string())?;
+        let token = Token::issue(user, DEFAULT_TOKEN_TTL);
+        self.tokens.insert(token.value.clone(), token.clone());
+        Ok(token)
+    }
+
+    /// Look up a stored token by its value.
+    pub fn find_token(&self, value: &str) -> Option<Token> {
+        self.tokens.get(value).cloned()
+    }
+
+    /// Revoke a token, returning whether one was removed.
+    pub fn revoke_token(&mut self, value: &str) -> bool {
+        self.tokens.remove(value).is_some()
+    }
+
+    /// Issue a fresh email-verification token for `user_id`, replacing any
+    /// prior token for that user. The nonce is a URL-safe base58 encoding of
+    /// 24 random bytes and is guaranteed unique across outstanding tokens.
+    ///
+    /// Fails if no such user exists rather than fabricating a credential for a
+    /// phantom account.
+    pub fn issue_email_token(&mut self, user_id: u64) -> Result<EmailTokenCredential, String> {
+        let (username, email) = match self.users.get(&user_id) {
+            Some(u) => (u.name.clone(), u.email.clone()),
+            None => return Err("User not found".to_string()),
+        };
+
+        // Drop any existing token belonging to this user.
+        self.email_tokens.retain(|_, t| t.user_id != user_id);
+
+        let nonce = loop {
+            let mut bytes = [0u8; 24];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            let candidate = bs58::encode(&bytes).into_string();
+            if !self.email_tokens.contains_key(&candidate) {
+                break candidate;
+            }
+        };
+
+        let credential = EmailTokenCredential {
+            user_id,
+            username,
+            email,
+            nonce: nonce.clone(),
+            expires_at: std::time::SystemTime::now() + std::time::Duration::from_secs(24 * 60 * 60),
+        };
+        self.email_tokens.insert(nonce, credential.clone());
+        Ok(credential)
+    }
+
+    /// Create an `Invited` account for `email` with no usable password. The
+    /// account only becomes usable once it is set `Active`, either by
+    /// [`activate`](Self::activate) or by
+    /// [`confirm_email_token`](Self::confirm_email_token).
+    pub fn invite(&mut self, email: String) -> Result<User, String> {
+        if !validate_email(&email) {
+            return Err("Invalid email format".to_string());
         }
+        let mut user = User {
+            id: 0,
+            name: email.clone(),
+            email,
+            status: AccountStatus::Invited,
+            password_hash: Vec::new(),
+            salt: Vec::new(),
+            password_iterations: 0,
+            client_kdf_type: ClientKdfType::default(),
+            role: UserRole::default(),
+            security_stamp: String::new(),
+            stamp_exception: None,
+        };
+        user.rotate_security_stamp();
+        let id = self.next_id;
+        self.save(user)?;
+        self.find_by_id(id)
+            .ok_or_else(|| "Failed to persist invited user".to_string())
+    }
+
+    /// Mark an account as `Active`, making it usable.
+    pub fn activate(&mut self, id: u64) -> Result<(), String> {
+        let user = self.users.get_mut(&id).ok_or_else(|| "User not found".to_string())?;
+        user.status = AccountStatus::Active;
+        Ok(())
+    }
+
+    /// Mark an account as `Disabled` without removing it.
+    pub fn disable(&mut self, id: u64) -> Result<(), String> {
+        let user = self.users.get_mut(&id).ok_or_else(|| "User not found".to_string())?;
+        user.status = AccountStatus::Disabled;
+        Ok(())
+    }
+
+    /// Look up an account, distinguishing a disabled account (`Err`) from a
+    /// missing one (`Ok(None)`); any other account is returned as `Ok(Some(_))`.
+    pub fn find_enabled_by_id(&self, id: u64) -> Result<Option<User>, String> {
+        match self.users.get(&id) {
+            Some(user) if user.status == AccountStatus::Disabled => {
+                Err("Account is disabled".to_string())
+            }
+            Some(user) => Ok(Some(user.clone())),
+            None => Ok(None),
+        }
+    }
+
+    /// Verify `old_pw`, re-hash `new_pw`, and rotate the security stamp in a
+    /// single step so that every token issued before the change is invalidated.
+    pub fn update_password(&mut self, id: u64, old_pw: &str, new_pw: &str) -> Result<(), String> {
+        let user = self.users.get_mut(&id).ok_or_else(|| "User not found".to_string())?;
+        if !user.verify_password(old_pw) {
+            return Err("Current password is incorrect".to_string());
+        }
+        user.set_password(new_pw); // rotates the security stamp
+        Ok(())
+    }
+
+    /// Check whether `value` names a stored token that is unexpired and was
+    /// issued against `user`'s current security stamp (or an explicitly granted
+    /// stamp exception).
+    pub fn token_is_valid(&self, user: &User, value: &str) -> bool {
+        match self.tokens.get(value) {
+            Some(token) => {
+                let stamp_ok = token.security_stamp == user.security_stamp
+                    || user.stamp_exception.as_deref() == Some(value);
+                token.user_id == user.id
+                    && token.verify(std::time::SystemTime::now())
+                    && stamp_ok
+            }
+            None => false,
+        }
+    }
+
+    /// Delete a user on behalf of `actor`, rejecting the call unless the actor
+    /// is authorized for [`Action::DeleteUser`] (i.e. an `Admin`).
+    pub fn delete_as(&mut self, actor: &User, id: u64) -> Result<bool, String> {
+        authorize(actor, Action::DeleteUser)?;
+        Ok(self.delete(id))
+    }
+
+    /// Confirm an email address from a previously issued nonce. Rejects unknown
+    /// or expired nonces; on success the target user is activated and the token
+    /// is consumed.
+    pub fn confirm_email_token(&mut self, nonce: &str) -> Result<(), String> {
+        let credential = self
+            .email_tokens
+            .get(nonce)
+            .cloned()
+            .ok_or_else(|| "Unknown email token".to_string())?;
+
+        if credential.expires_at <= std::time::SystemTime::now() {
+            self.email_tokens.remove(nonce);
+            return Err("Email token has expired".to_string());
+        }
+
+        match self.users.get_mut(&credential.user_id) {
+            Some(user) => user.status = AccountStatus::Active,
+            None => {
+                self.email_tokens.remove(nonce);
+                return Err("Token refers to a missing user".to_string());
+            }
+        }
+
+        self.email_tokens.remove(nonce);
+        Ok(())
     }
     
     pub fn seed_data(&mut self) {
-        let admin = User {
+        let mut admin = User {
             id: self.next_id,
             name: "Admin User".to_string(),
             email: "admin@example.com".to_string(),
-            active: true,
+            status: AccountStatus::Active,
+            password_hash: Vec::new(),
+            salt: Vec::new(),
+            password_iterations: 0,
+            client_kdf_type: ClientKdfType::default(),
+            role: UserRole::Admin,
+            security_stamp: String::new(),
+            stamp_exception: None,
         };
+        admin.set_password("admin");
         self.users.insert(self.next_id, admin);
         self.next_id += 1;
     }
@@ -69,27 +518,40 @@ impl Repository<User> for InMemoryUserRepository {
     fn delete(&mut self, id: u64) -> bool {
         self.users.remove(&id).is_some()
     }
+
+    fn all(&self) -> Vec<User> {
+        self.users.values().cloned().collect()
+    }
 }
 
 pub fn validate_email(email: &str) -> bool {
     email.contains('@') && email.contains('.')
 }
 
-pub fn create_user(name: String, email: String) -> Result<User, String> {
+pub fn create_user(name: String, email: String, password: &str) -> Result<User, String> {
     if name.trim().is_empty() {
         return Err("Name cannot be empty".to_string());
     }
-    
+
     if !validate_email(&email) {
         return Err("Invalid email format".to_string());
     }
-    
-    Ok(User {
+
+    let mut user = User {
         id: 0, // Will be assigned by repository
         name,
         email,
-        active: true,
-    })
+        status: AccountStatus::Invited, // Not usable until activated.
+        password_hash: Vec::new(),
+        salt: Vec::new(),
+        password_iterations: 0,
+        client_kdf_type: ClientKdfType::default(),
+        role: UserRole::default(),
+        security_stamp: String::new(),
+        stamp_exception: None,
+    };
+    user.set_password(password);
+    Ok(user)
 }
 
 macro_rules! log_info {
@@ -99,18 +561,65 @@ macro_rules! log_info {
 }
 
 pub mod utils {
-    pub fn hash_password(password: &str) -> String {
-        // Simple hash implementation for demo
-        format!("hashed_{}", password)
+    use super::{Token, User, DEFAULT_TOKEN_TTL};
+
+    /// Mint a fresh [`Token`] for `user` with the default lifetime.
+    ///
+    /// The token carries 32 CSPRNG bytes and the user's current security stamp.
+    /// It is not stored anywhere, so
+    /// [`InMemoryUserRepository::token_is_valid`](crate::InMemoryUserRepository::token_is_valid)
+    /// does not accept it; only tokens minted through
+    /// [`InMemoryUserRepository::issue_token`](crate::InMemoryUserRepository::issue_token)
+    /// are stored and checked against the stamp.
+    pub fn generate_token(user: &User) -> Token {
+        Token::issue(user, DEFAULT_TOKEN_TTL)
     }
-    
-    pub fn generate_token() -> String {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        format!("token_{}", timestamp)
+}
+
+/// Programmatic account-management operations over any [`Repository<User>`],
+/// modeled on the `admin accounts` subcommands found in operator CLIs.
+pub mod admin {
+    use super::{create_user, AccountStatus, Repository, User, UserRole};
+    use std::collections::HashSet;
+
+    /// Create and persist an account with the given `role`, validating the
+    /// email and hashing the password along the way. Returns the stored user.
+    pub fn create_account<R: Repository<User>>(
+        repo: &mut R,
+        name: String,
+        email: String,
+        role: UserRole,
+        password: &str,
+    ) -> Result<User, String> {
+        let mut user = create_user(name, email, password)?;
+        user.role = role;
+        // The repository assigns the id on save. Email is not unique, so capture
+        // the existing ids up front and recover the one that appears after save
+        // rather than scanning by a non-unique field.
+        let before: HashSet<u64> = repo.all().into_iter().map(|u| u.id).collect();
+        repo.save(user)?;
+        repo.all()
+            .into_iter()
+            .find(|u| !before.contains(&u.id))
+            .ok_or_else(|| "Failed to persist account".to_string())
+    }
+
+    /// List accounts, optionally filtered by `status` and/or `role`.
+    pub fn list_accounts<R: Repository<User>>(
+        repo: &R,
+        status: Option<AccountStatus>,
+        role: Option<UserRole>,
+    ) -> Vec<User> {
+        repo.all()
+            .into_iter()
+            .filter(|u| status.is_none_or(|s| u.status == s))
+            .filter(|u| role.is_none_or(|r| u.role == r))
+            .collect()
+    }
+
+    /// Delete an account by id, returning whether one was removed.
+    pub fn delete_account<R: Repository<User>>(repo: &mut R, id: u64) -> bool {
+        repo.delete(id)
     }
 }
 
@@ -120,21 +629,177 @@ mod tests {
     
     #[test]
     fn test_user_creation() {
-        let user = create_user("John Doe".to_string(), "john@example.com".to_string());
+        let user = create_user("John Doe".to_string(), "john@example.com".to_string(), "hunter2");
         assert!(user.is_ok());
     }
-    
+
     #[test]
     fn test_repository() {
         let mut repo = InMemoryUserRepository::new();
-        let user = User {
+        let mut user = User {
             id: 1,
             name: "Test User".to_string(),
             email: "test@example.com".to_string(),
-            active: true,
+            status: AccountStatus::Active,
+            password_hash: Vec::new(),
+            salt: Vec::new(),
+            password_iterations: 0,
+            client_kdf_type: ClientKdfType::default(),
+            role: UserRole::default(),
+            security_stamp: String::new(),
+            stamp_exception: None,
         };
-        
+        user.set_password("hunter2");
+
         assert!(repo.save(user).is_ok());
         assert!(repo.find_by_id(1).is_some());
     }
+
+    #[test]
+    fn test_role_gated_delete() {
+        let mut repo = InMemoryUserRepository::new();
+        repo.seed_data(); // id 1, Admin
+        let target = create_user("Tgt".to_string(), "t@example.com".to_string(), "pw").unwrap();
+        repo.save(target).unwrap(); // id 2, Viewer
+
+        let viewer = repo.find_by_id(2).unwrap();
+        assert!(repo.delete_as(&viewer, 1).is_err());
+
+        let admin = repo.find_by_id(1).unwrap();
+        assert_eq!(repo.delete_as(&admin, 2), Ok(true));
+    }
+
+    #[test]
+    fn test_update_password_invalidates_tokens() {
+        let mut repo = InMemoryUserRepository::new();
+        let user = create_user("Sam".to_string(), "sam@example.com".to_string(), "old").unwrap();
+        repo.save(user).unwrap();
+
+        let before = repo.find_by_id(1).unwrap();
+        let token = repo.issue_token(1).unwrap();
+        assert!(repo.token_is_valid(&before, &token.value));
+
+        assert!(repo.update_password(1, "wrong", "new").is_err());
+        assert!(repo.update_password(1, "old", "new").is_ok());
+
+        let after = repo.find_by_id(1).unwrap();
+        assert!(after.verify_password("new"));
+        // The pre-rotation token no longer validates against the new stamp.
+        assert!(!repo.token_is_valid(&after, &token.value));
+
+        let fresh = repo.issue_token(1).unwrap();
+        assert!(repo.token_is_valid(&after, &fresh.value));
+    }
+
+    #[test]
+    fn test_admin_account_management() {
+        let mut repo = InMemoryUserRepository::new();
+
+        let editor = admin::create_account(
+            &mut repo,
+            "Ed".to_string(),
+            "ed@example.com".to_string(),
+            UserRole::Editor,
+            "pw",
+        )
+        .unwrap();
+        assert_eq!(editor.role, UserRole::Editor);
+        assert!(editor.verify_password("pw"));
+
+        admin::create_account(
+            &mut repo,
+            "Vi".to_string(),
+            "vi@example.com".to_string(),
+            UserRole::Viewer,
+            "pw",
+        )
+        .unwrap();
+
+        assert_eq!(admin::list_accounts(&repo, None, None).len(), 2);
+        let editors = admin::list_accounts(&repo, None, Some(UserRole::Editor));
+        assert_eq!(editors.len(), 1);
+        assert_eq!(
+            admin::list_accounts(&repo, Some(AccountStatus::Invited), None).len(),
+            2
+        );
+
+        assert!(admin::delete_account(&mut repo, editor.id));
+        assert_eq!(admin::list_accounts(&repo, None, None).len(), 1);
+    }
+
+    #[test]
+    fn test_token_expiry_and_revocation() {
+        use std::time::{Duration, SystemTime};
+
+        let mut repo = InMemoryUserRepository::new();
+        let user = create_user("Tok".to_string(), "tok@example.com".to_string(), "pw").unwrap();
+        repo.save(user).unwrap();
+
+        let token = repo.issue_token(1).unwrap();
+        assert!(token.value.len() >= 32);
+        assert!(token.verify(SystemTime::now()));
+        assert!(!token.verify(token.expires_at + Duration::from_secs(1)));
+
+        assert!(repo.find_token(&token.value).is_some());
+        assert!(repo.revoke_token(&token.value));
+        assert!(repo.find_token(&token.value).is_none());
+    }
+
+    #[test]
+    fn test_account_lifecycle() {
+        let mut repo = InMemoryUserRepository::new();
+        let invited = repo.invite("new@example.com".to_string()).unwrap();
+        assert_eq!(invited.status, AccountStatus::Invited);
+        assert!(invited.password_hash.is_empty());
+
+        repo.activate(invited.id).unwrap();
+        assert_eq!(repo.find_by_id(invited.id).unwrap().status, AccountStatus::Active);
+
+        repo.disable(invited.id).unwrap();
+        // A disabled account is reported distinctly from a missing one.
+        assert!(repo.find_enabled_by_id(invited.id).is_err());
+        assert!(repo.find_enabled_by_id(9999).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_password_set_and_verify() {
+        let user = create_user("Jane".to_string(), "jane@example.com".to_string(), "s3cret").unwrap();
+        assert!(!user.password_hash.is_empty());
+        assert_eq!(user.salt.len(), 16);
+        assert!(user.verify_password("s3cret"));
+        assert!(!user.verify_password("wrong"));
+    }
+
+    #[test]
+    fn test_email_token_confirmation() {
+        let mut repo = InMemoryUserRepository::new();
+        let user = create_user("Pat".to_string(), "pat@example.com".to_string(), "pw").unwrap();
+        repo.save(user).unwrap();
+
+        let token = repo.issue_email_token(1).unwrap();
+        assert!(!token.nonce.is_empty());
+        // Issuing for a nonexistent user is an error, not a phantom credential.
+        assert!(repo.issue_email_token(9999).is_err());
+        assert_eq!(repo.find_by_id(1).unwrap().status, AccountStatus::Invited);
+
+        assert!(repo.confirm_email_token(&token.nonce).is_ok());
+        assert_eq!(repo.find_by_id(1).unwrap().status, AccountStatus::Active);
+
+        // Token is single-use.
+        assert!(repo.confirm_email_token(&token.nonce).is_err());
+    }
+
+    #[test]
+    fn test_rehash_if_needed() {
+        let mut user = create_user("Joe".to_string(), "joe@example.com".to_string(), "pw").unwrap();
+        // Current hash is already at the default iteration count.
+        assert!(!user.rehash_if_needed("pw"));
+
+        // Simulate an older, weaker hash.
+        user.password_iterations = 10_000;
+        user.set_password_with_iterations("pw", 10_000);
+        assert!(user.rehash_if_needed("pw"));
+        assert_eq!(user.password_iterations, DEFAULT_PASSWORD_ITERATIONS);
+        assert!(user.verify_password("pw"));
+    }
 } 
\ No newline at end of file